@@ -0,0 +1,47 @@
+/// Picks the best media type a client can receive, given the raw `Accept` header value and
+/// the list of media types this route actually knows how to serve (in preference order: when a
+/// wildcard matches several, the first listed wins). Ranks candidates by their `q` parameter
+/// (default `1.0`, per RFC 7231 §5.3.2); entries with equal `q` are tried in the order the
+/// header lists them. Understands `*/*` and `type/*` wildcards. Returns `None` if nothing in
+/// `supported` matches.
+pub fn negotiate(accept: &str, supported: &[&str]) -> Option<String> {
+    let mut ranked: Vec<(f32, &str)> = accept
+        .split(',')
+        .filter_map(|candidate| {
+            let mut segments = candidate.split(';');
+            let media_type = segments.next()?.trim();
+            if media_type.is_empty() {
+                return None;
+            }
+
+            let q = segments
+                .find_map(|segment| segment.trim().strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            Some((q, media_type))
+        })
+        .collect();
+
+    ranked.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+    ranked.into_iter().find_map(|(_, pattern)| {
+        supported
+            .iter()
+            .find(|candidate| media_type_matches(pattern, candidate))
+            .map(|candidate| candidate.to_string())
+    })
+}
+
+fn media_type_matches(pattern: &str, candidate: &str) -> bool {
+    if pattern == "*/*" {
+        return true;
+    }
+
+    match pattern.strip_suffix("/*") {
+        Some(type_prefix) => candidate
+            .split_once('/')
+            .is_some_and(|(candidate_type, _)| candidate_type.eq_ignore_ascii_case(type_prefix)),
+        None => pattern.eq_ignore_ascii_case(candidate),
+    }
+}
@@ -5,9 +5,12 @@ use std::{
     time::Duration,
 };
 
+use plotters::style::RGBColor;
 use serde::{Deserialize, Serialize};
 
 use crate::error::Error;
+use crate::lttb;
+use crate::render::{self, DEFAULT_HEIGHT, DEFAULT_WIDTH};
 
 #[derive(Serialize)]
 pub enum ChartType {
@@ -15,7 +18,13 @@ pub enum ChartType {
     PassThru(usize),
 }
 
-#[derive(Serialize, Deserialize)]
+/// Caps how many points a `ChartType::Stack` line keeps, since unlike `PassThru` it has no
+/// viewport and would otherwise grow without bound. Once a line hits the cap, the oldest
+/// point is dropped on every subsequent insert.
+#[derive(Clone, Copy, Serialize)]
+pub struct MaxPoints(pub usize);
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct LineColor {
     r: u8,
     g: u8,
@@ -26,6 +35,10 @@ impl LineColor {
     pub fn init(r: u8, g: u8, b: u8) -> Self {
         Self { r, g, b }
     }
+
+    pub fn into_rgb_color(&self) -> RGBColor {
+        RGBColor(self.r, self.g, self.b)
+    }
 }
 
 #[derive(Serialize)]
@@ -34,12 +47,19 @@ pub struct ChartInfo {
     chart_type: ChartType,
     interval: std::time::Duration,
     y_range: Range<f64>,
+    max_points: Option<MaxPoints>,
 }
 
+/// Number of points buffered per subscriber before the oldest unsent point is dropped.
+/// Lagging SSE clients simply miss a few points rather than blocking `insert_data`.
+const BROADCAST_CAPACITY: usize = 256;
+
 #[derive(Serialize)]
 pub struct Chart {
     info: ChartInfo,
     lines: std::collections::HashMap<String, (VecDeque<f64>, LineColor)>,
+    #[serde(skip)]
+    sender: tokio::sync::broadcast::Sender<(String, f64)>,
 }
 
 impl Chart {
@@ -48,21 +68,30 @@ impl Chart {
         chart_type: ChartType,
         interval: std::time::Duration,
         y_range: Range<f64>,
+        max_points: Option<MaxPoints>,
     ) -> Self {
+        let (sender, _) = tokio::sync::broadcast::channel(BROADCAST_CAPACITY);
+
         Chart {
             info: ChartInfo {
                 caption,
                 chart_type,
                 interval,
                 y_range,
+                max_points,
             },
             lines: std::collections::HashMap::new(),
+            sender,
         }
     }
     fn new_label(&mut self, label: String, color: LineColor) {
         self.lines.insert(label, (VecDeque::new(), color));
     }
 
+    fn subscribe(&self) -> tokio::sync::broadcast::Receiver<(String, f64)> {
+        self.sender.subscribe()
+    }
+
     fn insert_data(&mut self, label: String, data: f64) -> Result<(), Error> {
         let (line, _) = self
             .lines
@@ -72,6 +101,12 @@ impl Chart {
         match &self.info.chart_type {
             ChartType::Stack => {
                 line.push_back(data);
+
+                if let Some(MaxPoints(cap)) = self.info.max_points {
+                    while line.len() > cap {
+                        line.pop_front();
+                    }
+                }
             }
             ChartType::PassThru(viewport_size) => {
                 if line.len() == *viewport_size {
@@ -81,9 +116,24 @@ impl Chart {
             }
         }
 
+        // Subscribers come and go; nobody listening just means nobody to tell.
+        let _ = self.sender.send((label, data));
+
         Ok(())
     }
 
+    /// Applies every point, returning the labels that didn't match an existing line instead
+    /// of aborting on the first one, so one bad label in a frame doesn't lose the rest.
+    fn insert_batch(&mut self, points: Vec<(String, f64)>) -> Vec<String> {
+        points
+            .into_iter()
+            .filter_map(|(label, data)| {
+                let unknown = label.clone();
+                self.insert_data(label, data).err().map(|_| unknown)
+            })
+            .collect()
+    }
+
     fn resize_viewport(&mut self, size: usize) {
         match self.info.chart_type {
             ChartType::Stack => return,
@@ -96,6 +146,26 @@ impl Chart {
     }
 }
 
+/// Applies LTTB downsampling to every line, returning `None` (use the lines as-is) when
+/// `target` is `None`, matching the "no downsampling by default" backward-compat requirement.
+fn downsample_lines(
+    lines: &std::collections::HashMap<String, (VecDeque<f64>, LineColor)>,
+    target: Option<usize>,
+) -> Option<std::collections::HashMap<String, (VecDeque<f64>, LineColor)>> {
+    let target = target?;
+
+    Some(
+        lines
+            .iter()
+            .map(|(label, (line, color))| {
+                let values: Vec<f64> = line.iter().copied().collect();
+                let sampled = lttb::lttb(&values, target);
+                (label.clone(), (VecDeque::from(sampled), color.clone()))
+            })
+            .collect(),
+    )
+}
+
 pub struct Charts {
     charts: RwLock<endorphin::HashMap<usize, RwLock<Chart>, endorphin::policy::TTIPolicy>>,
 }
@@ -147,9 +217,65 @@ impl Charts {
         Ok(())
     }
 
+    /// Takes the chart's write lock once and applies every point atomically, instead of the
+    /// one-lock-per-point churn a client calling `insert_data` in a loop would cause. Returns
+    /// the labels that didn't exist on the chart rather than failing the whole batch.
+    pub fn insert_batch(
+        &self,
+        index: usize,
+        points: Vec<(String, f64)>,
+    ) -> Result<Vec<String>, Error> {
+        let read_lock = self.charts.read().unwrap();
+        let mut chart = read_lock
+            .get(&index)
+            .ok_or(Error::InvalidChartNumberError)?
+            .write()
+            .unwrap();
+
+        Ok(chart.insert_batch(points))
+    }
+
     pub fn resize_viewport(&mut self, index: usize) {}
 
-    pub fn get_lines_as_json_string(&self, index: usize) -> Result<String, Error> {
+    /// Subscribes to live updates for a chart, returning the broadcast receiver alongside a
+    /// JSON snapshot of its current lines so a new `/stream/:index` client can seed its state
+    /// before the first incremental event arrives.
+    pub fn subscribe(
+        &self,
+        index: usize,
+    ) -> Result<(tokio::sync::broadcast::Receiver<(String, f64)>, String), Error> {
+        let chart_lock = self.charts.read().unwrap();
+        let chart = chart_lock
+            .get(&index)
+            .ok_or(Error::InvalidChartNumberError)?
+            .read()
+            .unwrap();
+
+        let snapshot = serde_json::to_string(&chart.lines)?;
+
+        Ok((chart.subscribe(), snapshot))
+    }
+
+    /// `points` is the target M for LTTB downsampling; `None` serves every buffered point,
+    /// preserving the pre-downsampling behavior of this endpoint.
+    pub fn get_lines_as_json_string(&self, index: usize, points: Option<usize>) -> Result<String, Error> {
+        let chart_lock = self.charts.read().unwrap();
+        let chart = chart_lock
+            .get(&index)
+            .ok_or(Error::InvalidChartNumberError)?
+            .read()
+            .unwrap();
+
+        match downsample_lines(&chart.lines, points) {
+            Some(sampled) => Ok(serde_json::to_string(&sampled)?),
+            None => Ok(serde_json::to_string(&chart.lines)?),
+        }
+    }
+
+    /// Renders a chart's lines as CSV: one column per line label, one row per sample index,
+    /// so the same data can be pulled into a spreadsheet instead of parsed as JSON. `points`
+    /// behaves as in [`Charts::get_lines_as_json_string`].
+    pub fn get_lines_as_csv_string(&self, index: usize, points: Option<usize>) -> Result<String, Error> {
         let chart_lock = self.charts.read().unwrap();
         let chart = chart_lock
             .get(&index)
@@ -157,7 +283,42 @@ impl Charts {
             .read()
             .unwrap();
 
-        Ok(serde_json::to_string(&chart.lines).unwrap())
+        let sampled = downsample_lines(&chart.lines, points);
+        let lines = sampled.as_ref().unwrap_or(&chart.lines);
+
+        let mut labels: Vec<&String> = lines.keys().collect();
+        labels.sort();
+
+        let mut csv = labels
+            .iter()
+            .map(|label| label.as_str())
+            .collect::<Vec<_>>()
+            .join(",");
+        csv.push('\n');
+
+        let row_count = labels
+            .iter()
+            .map(|label| lines[label.as_str()].0.len())
+            .max()
+            .unwrap_or(0);
+
+        for row in 0..row_count {
+            let values = labels
+                .iter()
+                .map(|label| {
+                    lines[label.as_str()]
+                        .0
+                        .get(row)
+                        .map(|value| value.to_string())
+                        .unwrap_or_default()
+                })
+                .collect::<Vec<_>>()
+                .join(",");
+            csv.push_str(&values);
+            csv.push('\n');
+        }
+
+        Ok(csv)
     }
 
     pub fn get_info_as_json_string(&self, index: usize) -> Result<String, Error> {
@@ -168,6 +329,62 @@ impl Charts {
             .read()
             .unwrap();
 
-        Ok(serde_json::to_string(&chart.info).unwrap())
+        Ok(serde_json::to_string(&chart.info)?)
+    }
+
+    pub fn render_png(&self, index: usize, points: Option<usize>) -> Result<Vec<u8>, Error> {
+        let chart_lock = self.charts.read().unwrap();
+        let chart = chart_lock
+            .get(&index)
+            .ok_or(Error::InvalidChartNumberError)?
+            .read()
+            .unwrap();
+
+        let sampled = downsample_lines(&chart.lines, points);
+        let lines = sampled.as_ref().unwrap_or(&chart.lines);
+
+        let mut buffer = vec![0u8; (DEFAULT_WIDTH * DEFAULT_HEIGHT * 3) as usize];
+        {
+            let backend = plotters_bitmap::BitMapBackend::with_buffer(
+                &mut buffer,
+                (DEFAULT_WIDTH, DEFAULT_HEIGHT),
+            );
+            render::draw_chart(backend, &chart.info, lines)
+                .map_err(|e| Error::RenderError(e.to_string()))?;
+        }
+
+        let image = image::RgbImage::from_raw(DEFAULT_WIDTH, DEFAULT_HEIGHT, buffer)
+            .ok_or_else(|| Error::RenderError("buffer has the wrong size for the image".into()))?;
+
+        let mut png = Vec::new();
+        image
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageOutputFormat::Png)
+            .map_err(|e| Error::RenderError(e.to_string()))?;
+
+        Ok(png)
+    }
+
+    pub fn render_svg(&self, index: usize, points: Option<usize>) -> Result<String, Error> {
+        let chart_lock = self.charts.read().unwrap();
+        let chart = chart_lock
+            .get(&index)
+            .ok_or(Error::InvalidChartNumberError)?
+            .read()
+            .unwrap();
+
+        let sampled = downsample_lines(&chart.lines, points);
+        let lines = sampled.as_ref().unwrap_or(&chart.lines);
+
+        let mut svg = String::new();
+        {
+            let backend = plotters::backend::SVGBackend::with_string(
+                &mut svg,
+                (DEFAULT_WIDTH, DEFAULT_HEIGHT),
+            );
+            render::draw_chart(backend, &chart.info, lines)
+                .map_err(|e| Error::RenderError(e.to_string()))?;
+        }
+
+        Ok(svg)
     }
 }
@@ -1,7 +1,41 @@
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
 use thiserror::Error;
 
+#[derive(Debug, Error)]
 pub enum Error {
+    #[error("no line with that label exists on this chart")]
     InvalidLineLabelError,
+    #[error("no chart exists at that index")]
     InvalidChartNumberError,
+    #[error("a chart already exists at that index")]
     AlreadyExistIndexError,
+    #[error("no content type for the requested file is supported")]
+    UnknownFileType,
+    #[error("{0}")]
+    BadRequest(String),
+    #[error("failed to render chart: {0}")]
+    RenderError(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Serialization(#[from] serde_json::Error),
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            Error::InvalidLineLabelError
+            | Error::InvalidChartNumberError
+            | Error::UnknownFileType
+            | Error::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Error::AlreadyExistIndexError => StatusCode::CONFLICT,
+            Error::Io(err) if err.kind() == std::io::ErrorKind::NotFound => StatusCode::NOT_FOUND,
+            Error::Io(_) | Error::Serialization(_) | Error::RenderError(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        };
+
+        (status, self.to_string()).into_response()
+    }
 }
@@ -0,0 +1,88 @@
+/// Largest-Triangle-Three-Buckets downsampling.
+///
+/// Reduces `data` (samples are treated as evenly spaced on the x axis, one unit apart) to at
+/// most `threshold` points while preserving the overall shape of the line: the first and last
+/// points are always kept, the rest are split into `threshold - 2` contiguous buckets, and for
+/// each bucket the point forming the largest triangle with the previously selected point and the
+/// average of the *next* bucket is kept. No-ops (returns `data` unchanged) if there is nothing to
+/// downsample, i.e. `threshold >= data.len()` or `threshold < 3`.
+pub fn lttb(data: &[f64], threshold: usize) -> Vec<f64> {
+    if threshold >= data.len() || threshold < 3 {
+        return data.to_vec();
+    }
+
+    let mut sampled = Vec::with_capacity(threshold);
+    sampled.push(data[0]);
+
+    let bucket_size = (data.len() - 2) as f64 / (threshold - 2) as f64;
+    let mut selected = 0usize;
+
+    for bucket in 0..threshold - 2 {
+        let bucket_start = (bucket as f64 * bucket_size) as usize + 1;
+        let bucket_end = (((bucket + 1) as f64 * bucket_size) as usize + 1).min(data.len() - 1);
+
+        let next_start = bucket_end;
+        let next_end = (((bucket + 2) as f64 * bucket_size) as usize + 1).min(data.len());
+        let next_avg = average_bucket(data, next_start, next_end);
+
+        let point_a = (selected as f64, data[selected]);
+
+        let mut max_area = -1.0;
+        let mut max_index = bucket_start;
+        for i in bucket_start..bucket_end.max(bucket_start + 1) {
+            let area = triangle_area(point_a, (i as f64, data[i]), next_avg);
+            if area > max_area {
+                max_area = area;
+                max_index = i;
+            }
+        }
+
+        sampled.push(data[max_index]);
+        selected = max_index;
+    }
+
+    sampled.push(data[data.len() - 1]);
+    sampled
+}
+
+fn average_bucket(data: &[f64], start: usize, end: usize) -> (f64, f64) {
+    let end = end.max(start + 1).min(data.len());
+    let len = (end - start) as f64;
+    let sum_x: f64 = (start..end).map(|i| i as f64).sum();
+    let sum_y: f64 = data[start..end].iter().sum();
+    (sum_x / len, sum_y / len)
+}
+
+/// |(A.x-C.x)(B.y-A.y) - (A.x-B.x)(C.y-A.y)| / 2
+fn triangle_area(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> f64 {
+    ((a.0 - c.0) * (b.1 - a.1) - (a.0 - b.0) * (c.1 - a.1)).abs() / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::lttb;
+
+    #[test]
+    fn preserves_endpoints() {
+        let data: Vec<f64> = (0..100).map(|i| i as f64).collect();
+        let sampled = lttb(&data, 10);
+
+        assert_eq!(sampled.first(), data.first());
+        assert_eq!(sampled.last(), data.last());
+    }
+
+    #[test]
+    fn output_length_equals_threshold() {
+        let data: Vec<f64> = (0..1000).map(|i| (i as f64).sin()).collect();
+
+        for threshold in [3, 10, 50, 200] {
+            assert_eq!(lttb(&data, threshold).len(), threshold);
+        }
+    }
+
+    #[test]
+    fn no_op_when_below_threshold() {
+        let data = vec![1.0, 2.0, 3.0];
+        assert_eq!(lttb(&data, 10), data);
+    }
+}
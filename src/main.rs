@@ -1,20 +1,28 @@
+mod accept;
 mod chart;
 mod error;
+mod lttb;
+mod render;
 
 use std::sync::Arc;
 use std::time::Duration;
 use std::{collections::HashMap, fs};
 
-use axum::http::StatusCode;
+use axum::http::{HeaderMap, StatusCode};
 use axum::{
     body::Body,
     extract::{Extension, Path, Query},
+    response::sse::{Event, Sse},
     response::{IntoResponse, Response},
-    routing::get,
+    routing::{get, post},
     Json, Router,
 };
 use chart::LineColor;
-use serde::Deserialize;
+use error::Error;
+use futures::stream::{self, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use tokio_stream::wrappers::BroadcastStream;
 
 use crate::chart::{Chart, ChartType, Charts};
 
@@ -29,6 +37,9 @@ async fn main() {
         .route("/plot/:index", get(get_chart_data))
         .route("/plot_info/:index", get(get_chart_info))
         .route("/insert/:index", get(insert_data))
+        .route("/insert_batch/:index", post(insert_batch_data))
+        .route("/render/:index_ext", get(render_chart))
+        .route("/stream/:index", get(stream_chart_data))
         .route("/www/pkg/:file_name", get(serve_file))
         .layer(Extension(charts));
 
@@ -41,7 +52,7 @@ async fn main() {
 async fn index(
     Extension(charts): Extension<Arc<Charts>>,
     Path(index): Path<usize>,
-) -> Result<Response<Body>, StatusCode> {
+) -> Result<Response<Body>, Error> {
     if charts.contains(index) {
         let bytes = include_bytes!("../www/index.html").to_vec();
         let len = bytes.len();
@@ -54,45 +65,52 @@ async fn index(
             .body(body)
             .unwrap())
     } else {
-        Err(StatusCode::BAD_REQUEST)
+        Err(Error::InvalidChartNumberError)
     }
 }
 
-async fn new_plot(
-    Extension(charts): Extension<Arc<Charts>>,
-    Query(params): Query<HashMap<String, String>>,
-) -> Result<Response<String>, StatusCode> {
-    let caption = params.get("caption").ok_or(StatusCode::BAD_REQUEST)?;
-    let chart_type = params.get("type").ok_or(StatusCode::BAD_REQUEST)?;
+fn required_param<'a>(
+    params: &'a HashMap<String, String>,
+    name: &str,
+) -> Result<&'a String, Error> {
+    params
+        .get(name)
+        .ok_or_else(|| Error::BadRequest(format!("missing query param '{name}'")))
+}
 
-    let y_start = params
-        .get("y_start")
-        .ok_or(StatusCode::BAD_REQUEST)?
+fn parse_param<T: std::str::FromStr>(params: &HashMap<String, String>, name: &str) -> Result<T, Error> {
+    required_param(params, name)?
         .parse()
-        .map_err(|_| StatusCode::BAD_REQUEST)?;
+        .map_err(|_| Error::BadRequest(format!("invalid query param '{name}'")))
+}
 
-    let y_end = params
-        .get("y_end")
-        .ok_or(StatusCode::BAD_REQUEST)?
-        .parse()
-        .map_err(|_| StatusCode::BAD_REQUEST)?;
+fn parse_optional_param<T: std::str::FromStr>(
+    params: &HashMap<String, String>,
+    name: &str,
+) -> Result<Option<T>, Error> {
+    match params.get(name) {
+        Some(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|_| Error::BadRequest(format!("invalid query param '{name}'"))),
+        None => Ok(None),
+    }
+}
 
-    let interval = params
-        .get("interval")
-        .ok_or(StatusCode::BAD_REQUEST)?
-        .parse()
-        .map_err(|_| StatusCode::BAD_REQUEST)?;
+async fn new_plot(
+    Extension(charts): Extension<Arc<Charts>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Response<String>, Error> {
+    let caption = required_param(&params, "caption")?;
+    let chart_type = required_param(&params, "type")?;
 
-    let index = params
-        .get("index")
-        .ok_or(StatusCode::BAD_REQUEST)?
-        .parse()
-        .map_err(|_| StatusCode::BAD_REQUEST)?;
-    let tti = params
-        .get("tti")
-        .ok_or(StatusCode::BAD_REQUEST)?
-        .parse()
-        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    let y_start = parse_param(&params, "y_start")?;
+    let y_end = parse_param(&params, "y_end")?;
+    let interval = parse_param(&params, "interval")?;
+    let index = parse_param(&params, "index")?;
+    let tti = parse_param(&params, "tti")?;
+
+    let max_points = parse_optional_param::<usize>(&params, "max_points")?.map(chart::MaxPoints);
 
     let chart_type = if chart_type == "stack" {
         ChartType::Stack
@@ -101,11 +119,13 @@ async fn new_plot(
             .get("viewport_size")
             .unwrap_or(&"40".to_string())
             .parse()
-            .map_err(|_| StatusCode::BAD_REQUEST)?;
+            .map_err(|_| Error::BadRequest("invalid query param 'viewport_size'".into()))?;
 
         ChartType::PassThru(viewport_size)
     } else {
-        return Err(StatusCode::BAD_REQUEST);
+        return Err(Error::BadRequest(
+            "query param 'type' must be 'stack' or 'pass-thru'".into(),
+        ));
     };
 
     let chart = Chart::new(
@@ -113,11 +133,10 @@ async fn new_plot(
         chart_type,
         Duration::from_millis(interval),
         y_start..y_end,
+        max_points,
     );
 
-    charts
-        .insert_chart(index, chart, Duration::from_millis(tti))
-        .map_err(|_| StatusCode::CONFLICT)?;
+    charts.insert_chart(index, chart, Duration::from_millis(tti))?;
 
     Ok(Response::builder()
         .body(format!("Success to make new chart in {index}"))
@@ -136,14 +155,12 @@ async fn new_label(
     Extension(charts): Extension<Arc<Charts>>,
     Path(index): Path<usize>,
     Query(label): Query<Label>,
-) -> Result<Response<String>, StatusCode> {
-    charts
-        .new_label(
-            index,
-            label.name,
-            LineColor::init(label.r, label.g, label.b),
-        )
-        .map_err(|_| StatusCode::BAD_REQUEST)?;
+) -> Result<Response<String>, Error> {
+    charts.new_label(
+        index,
+        label.name,
+        LineColor::init(label.r, label.g, label.b),
+    )?;
 
     Ok(Response::builder()
         .status(StatusCode::OK)
@@ -151,41 +168,63 @@ async fn new_label(
         .unwrap())
 }
 
+/// Reads the raw `Accept` header, defaulting to `*/*` (accept anything) when it is absent
+/// or not valid UTF-8, matching how most HTTP clients behave when they send no header at all.
+fn accept_header(headers: &HeaderMap) -> &str {
+    headers
+        .get(axum::http::header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("*/*")
+}
+
 async fn get_chart_data(
     Extension(charts): Extension<Arc<Charts>>,
     Path(index): Path<usize>,
-) -> Result<Response<Body>, StatusCode> {
-    let serialized_lines = charts
-        .get_lines_as_json_string(index)
-        .map_err(|_| StatusCode::BAD_REQUEST)?;
-    let len = serialized_lines.len();
-
-    let body = Body::from(serialized_lines);
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Result<Response<Body>, Error> {
+    // Absent by default so existing `/plot/:index` clients keep seeing every buffered point.
+    let points = parse_optional_param::<usize>(&params, "points")?;
+
+    let content_type = accept::negotiate(
+        accept_header(&headers),
+        &["application/json", "text/csv", "image/png", "image/svg+xml"],
+    )
+    .unwrap_or_else(|| "application/json".to_string());
+
+    let bytes: Vec<u8> = match content_type.as_str() {
+        "text/csv" => charts.get_lines_as_csv_string(index, points)?.into_bytes(),
+        "image/png" => charts.render_png(index, points)?,
+        "image/svg+xml" => charts.render_svg(index, points)?.into_bytes(),
+        _ => charts.get_lines_as_json_string(index, points)?.into_bytes(),
+    };
+    let len = bytes.len();
 
     Ok(Response::builder()
         .status(StatusCode::OK)
         .header("CONTENT-LENGTH", len)
-        .header("CONTENT-TYPE", "text/json")
-        .body(body)
+        .header("CONTENT-TYPE", content_type)
+        .body(Body::from(bytes))
         .unwrap())
 }
 
 async fn get_chart_info(
     Extension(charts): Extension<Arc<Charts>>,
     Path(index): Path<usize>,
-) -> Result<Response<Body>, StatusCode> {
-    let serialized_info = charts
-        .get_info_as_json_string(index)
-        .map_err(|_| StatusCode::BAD_REQUEST)?;
-    let len = serialized_info.len();
+    headers: HeaderMap,
+) -> Result<Response<Body>, Error> {
+    // Metadata has no sample rows to put in CSV columns, so JSON is the only format on offer.
+    let content_type = accept::negotiate(accept_header(&headers), &["application/json"])
+        .unwrap_or_else(|| "application/json".to_string());
 
-    let body = Body::from(serialized_info);
+    let serialized_info = charts.get_info_as_json_string(index)?;
+    let len = serialized_info.len();
 
     Ok(Response::builder()
         .status(StatusCode::OK)
         .header("CONTENT-LENGTH", len)
-        .header("CONTENT-TYPE", "text/json")
-        .body(body)
+        .header("CONTENT-TYPE", content_type)
+        .body(Body::from(serialized_info))
         .unwrap())
 }
 
@@ -199,10 +238,8 @@ async fn insert_data(
     Extension(charts): Extension<Arc<Charts>>,
     Path(index): Path<usize>,
     Query(data): Query<Data>,
-) -> Result<Response<String>, StatusCode> {
-    charts
-        .insert_data(index, data.label, data.value)
-        .map_err(|_| StatusCode::BAD_REQUEST)?;
+) -> Result<Response<String>, Error> {
+    charts.insert_data(index, data.label, data.value)?;
 
     Ok(Response::builder()
         .status(StatusCode::OK)
@@ -210,21 +247,110 @@ async fn insert_data(
         .unwrap())
 }
 
-async fn serve_file(Path(file_name): Path<String>) -> Response<Body> {
-    let file = fs::read(format!("./www/pkg/{}", file_name)).unwrap();
+/// Applies every point in the body in one chart write lock, returning the labels that had no
+/// matching line instead of aborting on the first `InvalidLineLabelError`.
+async fn insert_batch_data(
+    Extension(charts): Extension<Arc<Charts>>,
+    Path(index): Path<usize>,
+    Json(data): Json<Vec<Data>>,
+) -> Result<Response<String>, Error> {
+    let points = data.into_iter().map(|d| (d.label, d.value)).collect();
+    let unknown_labels = charts.insert_batch(index, points)?;
 
-    let mime = mime_guess::from_path(file_name)
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .body(serde_json::to_string(&unknown_labels)?)
+        .unwrap())
+}
+
+#[derive(Serialize)]
+struct DataPoint {
+    label: String,
+    value: f64,
+}
+
+/// Pushes live data to clients instead of having them poll `/plot/:index` on a timer.
+/// Emits one `snapshot` event with the chart's current lines, then one `data` event per
+/// point accepted by `insert_data` afterwards.
+async fn stream_chart_data(
+    Extension(charts): Extension<Arc<Charts>>,
+    Path(index): Path<usize>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, Error> {
+    let (receiver, snapshot) = charts.subscribe(index)?;
+
+    let initial = stream::once(async move { Ok(Event::default().event("snapshot").data(snapshot)) });
+
+    let updates = BroadcastStream::new(receiver).filter_map(|point| {
+        futures::future::ready(point.ok().map(|(label, value)| {
+            let data = serde_json::to_string(&DataPoint { label, value }).unwrap();
+            Ok(Event::default().event("data").data(data))
+        }))
+    });
+
+    Ok(Sse::new(initial.chain(updates)))
+}
+
+/// Serves `GET /render/:index.png` and `GET /render/:index.svg`, dispatching on the file
+/// extension of the `:index_ext` path segment (axum can't match a literal suffix within a
+/// dynamic segment, so the extension is parsed out here instead of via two distinct routes).
+async fn render_chart(
+    Extension(charts): Extension<Arc<Charts>>,
+    Path(index_ext): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Response<Body>, Error> {
+    let points = parse_optional_param::<usize>(&params, "points")?;
+
+    let (index, ext) = index_ext
+        .rsplit_once('.')
+        .ok_or_else(|| Error::BadRequest("expected a '.png' or '.svg' extension".into()))?;
+    let index: usize = index
+        .parse()
+        .map_err(|_| Error::BadRequest("invalid chart index".into()))?;
+
+    match ext {
+        "png" => {
+            let png = charts.render_png(index, points)?;
+            let len = png.len();
+
+            Ok(Response::builder()
+                .status(StatusCode::OK)
+                .header("CONTENT-LENGTH", len)
+                .header("CONTENT-TYPE", "image/png")
+                .body(Body::from(png))
+                .unwrap())
+        }
+        "svg" => {
+            let svg = charts.render_svg(index, points)?;
+            let len = svg.len();
+
+            Ok(Response::builder()
+                .status(StatusCode::OK)
+                .header("CONTENT-LENGTH", len)
+                .header("CONTENT-TYPE", "image/svg+xml")
+                .body(Body::from(svg))
+                .unwrap())
+        }
+        _ => Err(Error::BadRequest(format!(
+            "unsupported render extension '.{ext}'"
+        ))),
+    }
+}
+
+async fn serve_file(Path(file_name): Path<String>) -> Result<Response<Body>, Error> {
+    let file = fs::read(format!("./www/pkg/{}", file_name))?;
+
+    let mime = mime_guess::from_path(&file_name)
         .first()
-        .unwrap()
+        .ok_or(Error::UnknownFileType)?
         .to_string();
 
     let len = file.len();
 
     let body = Body::from(file);
-    Response::builder()
+    Ok(Response::builder()
         .status(StatusCode::OK)
         .header("CONTENT-LENGTH", len)
         .header("Content-type", mime)
         .body(body)
-        .unwrap()
+        .unwrap())
 }
@@ -0,0 +1,100 @@
+use std::collections::{HashMap, VecDeque};
+use std::ops::Range;
+
+use plotters::coord::types::RangedCoordf64;
+use plotters::prelude::*;
+
+use crate::chart::{ChartInfo, ChartType, LineColor};
+
+/// Default canvas size used by the `/render/:index.png` and `/render/:index.svg` routes.
+pub const DEFAULT_WIDTH: u32 = 800;
+pub const DEFAULT_HEIGHT: u32 = 400;
+
+/// Draws every line of a chart onto `backend`, mirroring the stepping logic used by the
+/// WASM `Plot::draw_stack_chart` / `Plot::draw_pass_thru_chart` (`d = (x_end - x_start) / (n - 1)`).
+/// For a stack chart `n` is the length of the longest line (at least 2), while the canvas takes
+/// the first line's length, so the two differ when lines hold different numbers of points.
+pub fn draw_chart<DB: DrawingBackend>(
+    backend: DB,
+    info: &ChartInfo,
+    lines: &HashMap<String, (VecDeque<f64>, LineColor)>,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let drawing_area = backend.into_drawing_area();
+    drawing_area.fill(&WHITE)?;
+
+    let font: FontDesc = ("sans-serif", 20.0).into();
+    let mut chart = ChartBuilder::on(&drawing_area)
+        .caption(info.caption.clone(), font)
+        .x_label_area_size(30u32)
+        .y_label_area_size(30u32)
+        .margin_left(10)
+        .build_cartesian_2d(0.0..100.0, info.y_range.clone())?;
+
+    chart
+        .configure_mesh()
+        .disable_mesh()
+        .x_label_formatter(&|_| "".to_string())
+        .draw()?;
+
+    let mut data: Vec<(&String, &VecDeque<f64>, &LineColor)> = lines
+        .iter()
+        .map(|(label, (line, color))| (label, line, color))
+        .collect();
+    data.sort_by(|a, b| a.0.cmp(b.0));
+
+    match &info.chart_type {
+        ChartType::Stack => draw_series(&mut chart, &data, None),
+        ChartType::PassThru(viewport_size) => {
+            draw_series(&mut chart, &data, Some(*viewport_size))
+        }
+    }
+
+    chart
+        .configure_series_labels()
+        .position(SeriesLabelPosition::UpperRight)
+        .margin(20)
+        .legend_area_size(5)
+        .label_font(("Calibri", 15))
+        .draw()?;
+
+    drawing_area.present()?;
+
+    Ok(())
+}
+
+fn draw_series<DB: DrawingBackend>(
+    chart: &mut ChartContext<DB, Cartesian2d<RangedCoordf64, RangedCoordf64>>,
+    data: &[(&String, &VecDeque<f64>, &LineColor)],
+    viewport_size: Option<usize>,
+) {
+    let len = viewport_size.unwrap_or_else(|| {
+        data.iter()
+            .map(|(_, line, _)| line.len())
+            .max()
+            .unwrap_or(1)
+    });
+    let x_range: Range<f64> = chart.x_range();
+    let d = (x_range.end - x_range.start) / (len.max(2) - 1) as f64;
+
+    for (label, line, color) in data {
+        let mut i = viewport_size.map_or(0, |v| v - line.len());
+        let color = color.into_rgb_color();
+
+        let _ = chart
+            .draw_series(LineSeries::new(
+                line.iter().map(|y| {
+                    let ret = (i as f64 * d, *y);
+                    i += 1;
+                    ret
+                }),
+                color,
+            ))
+            .map(|s| {
+                s.label((*label).clone())
+                    .legend(move |(x, y)| Rectangle::new([(x - 10, y + 1), (x, y)], color));
+            });
+    }
+}
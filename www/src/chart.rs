@@ -9,7 +9,7 @@ pub enum ChartType {
     PassThru(usize),
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 pub struct LineColor {
     r: u8,
     g: u8,
@@ -41,6 +41,7 @@ pub struct Plot {
     info: ChartInfo,
     drawing_area: DrawingArea<CanvasBackend, Shift>,
     chart: ChartState<Cartesian2d<RangedCoordf64, RangedCoordf64>>,
+    data: HashMap<String, (VecDeque<f64>, LineColor)>,
 }
 
 #[wasm_bindgen]
@@ -76,20 +77,45 @@ impl Plot {
             info,
             chart: chart.into_chart_state(),
             drawing_area,
+            data: HashMap::new(),
         })
     }
 
+    /// Replaces all lines from a full `HashMap` snapshot, e.g. the legacy `/plot/:index`
+    /// poll response or the `snapshot` event a `/stream/:index` client gets on connect.
     pub fn update(&mut self, data: String) -> Result<(), JsValue> {
-        let data: HashMap<String, (VecDeque<f64>, LineColor)> =
-            serde_json::from_str(&data).expect("data deserialization failed");
+        self.data = serde_json::from_str(&data).expect("data deserialization failed");
+        self.redraw()
+    }
+
+    /// Applies one `label`/`value` point pushed over the `/stream/:index` SSE route, so a
+    /// live client doesn't have to re-deserialize the whole `HashMap` on every tick.
+    pub fn push_point(&mut self, label: String, value: f64) -> Result<(), JsValue> {
+        if let Some((line, _)) = self.data.get_mut(&label) {
+            match self.info.chart_type {
+                chart::ChartType::Stack => line.push_back(value),
+                chart::ChartType::PassThru(viewport_size) => {
+                    if line.len() == viewport_size {
+                        line.pop_front();
+                    }
+                    line.push_back(value);
+                }
+            }
+        }
+
+        self.redraw()
+    }
+
+    fn redraw(&mut self) -> Result<(), JsValue> {
         let state = self.chart.clone();
         let mut chart = state.restore(&self.drawing_area);
 
         chart.plotting_area().fill(&WHITE).unwrap();
 
-        let mut data: Vec<(String, VecDeque<f64>, LineColor)> = data
-            .into_iter()
-            .map(|(label, (lines, color))| (label, lines, color))
+        let mut data: Vec<(String, VecDeque<f64>, LineColor)> = self
+            .data
+            .iter()
+            .map(|(label, (lines, color))| (label.clone(), lines.clone(), color.clone()))
             .collect();
         data.sort_by(|a, b| a.0.cmp(&b.0));
 